@@ -1,62 +1,187 @@
-use std::iter::Iterator;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::iter::{Iterator, Peekable};
 
-#[derive(Debug)]
-pub struct ExpandableStringSplit<'a> {
+/// Controls the delimiter and variable-name policy used while splitting.
+///
+/// Build one with [`ExpandOptions::new`] (or `Default::default`) and
+/// customize it with [`ExpandOptions::delimiter`] /
+/// [`ExpandOptions::name_char_predicate`], then pass it to
+/// [`split_expandable_string_with_options`].
+pub struct ExpandOptions<'p> {
+    open: char,
+    close: char,
+    is_valid_name_char: Box<dyn Fn(char) -> bool + 'p>,
+}
+
+fn is_default_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+impl<'p> ExpandOptions<'p> {
+    pub fn new() -> Self {
+        Self {
+            open: '%',
+            close: '%',
+            is_valid_name_char: Box::new(is_default_name_char as fn(char) -> bool),
+        }
+    }
+
+    /// Sets a single delimiter character used both to open and close a
+    /// variable token, e.g. `%` for `%NAME%`.
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.open = delimiter;
+        self.close = delimiter;
+        self
+    }
+
+    /// Sets distinct opening and closing delimiters, e.g. `{` / `}` for a
+    /// `{NAME}` brace style.
+    pub fn open_close_delimiters(mut self, open: char, close: char) -> Self {
+        self.open = open;
+        self.close = close;
+        self
+    }
+
+    /// Sets the predicate a character must satisfy to be part of a variable
+    /// name. The default policy accepts ASCII alphanumerics and `_`.
+    pub fn name_char_predicate<P>(mut self, predicate: P) -> Self
+    where
+        P: Fn(char) -> bool + 'p,
+    {
+        self.is_valid_name_char = Box::new(predicate);
+        self
+    }
+}
+
+impl<'p> Default for ExpandOptions<'p> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct ExpandableStringSplit<'a, 'p> {
     src: &'a str,
-    chars_iter: std::str::CharIndices<'a>,
+    chars_iter: Peekable<std::str::CharIndices<'a>>,
+    open: char,
+    close: char,
+    is_valid_name_char: Box<dyn Fn(char) -> bool + 'p>,
     token_start: usize,
     reading_var: bool,
     done: bool,
+    /// An entry already computed while handling a doubled-delimiter escape,
+    /// waiting to be returned by the next call to `next` instead of the text
+    /// preceding it.
+    pending: Option<ExpandableStrSplitResult<'a>>,
 }
 
-pub fn split_expandable_string(s: &str) -> ExpandableStringSplit {
+impl<'a, 'p> std::fmt::Debug for ExpandableStringSplit<'a, 'p> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExpandableStringSplit")
+            .field("src", &self.src)
+            .field("open", &self.open)
+            .field("close", &self.close)
+            .field("token_start", &self.token_start)
+            .field("reading_var", &self.reading_var)
+            .field("done", &self.done)
+            .field("pending", &self.pending)
+            .finish_non_exhaustive()
+    }
+}
+
+pub fn split_expandable_string(s: &str) -> ExpandableStringSplit<'_, 'static> {
+    split_expandable_string_with_options(s, ExpandOptions::default())
+}
+
+pub fn split_expandable_string_with_options<'a, 'p>(
+    s: &'a str,
+    options: ExpandOptions<'p>,
+) -> ExpandableStringSplit<'a, 'p> {
     ExpandableStringSplit {
-        chars_iter: s.char_indices(),
+        chars_iter: s.char_indices().peekable(),
         src: s,
+        open: options.open,
+        close: options.close,
+        is_valid_name_char: options.is_valid_name_char,
         token_start: 0,
         reading_var: false,
         done: false,
+        pending: None,
     }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum ExpandableStrEntry<'a> {
     Substr(&'a str),
-    Var(&'a str),
+    Var {
+        name: &'a str,
+        /// Fallback text from a `%NAME:-default%` token, used when `NAME` has no value.
+        default: Option<&'a str>,
+    },
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum ExpandableStrSplitError {
     InvalidFormat,
+    InvalidVariableName,
 }
 
 pub type ExpandableStrSplitResult<'a> = Result<ExpandableStrEntry<'a>, ExpandableStrSplitError>;
 
-impl<'a> Iterator for ExpandableStringSplit<'a> {
+impl<'a, 'p> ExpandableStringSplit<'a, 'p> {
+    /// Splits a raw variable token on the first `:-` into `(name, default)`,
+    /// validating the name against the active character policy.
+    fn parse_var_token(&self, token: &'a str) -> ExpandableStrSplitResult<'a> {
+        let (name, default) = match token.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (token, None),
+        };
+
+        if name.is_empty() || !name.chars().all(|c| (self.is_valid_name_char)(c)) {
+            return Err(ExpandableStrSplitError::InvalidVariableName);
+        }
+
+        Ok(ExpandableStrEntry::Var { name, default })
+    }
+}
+
+impl<'a, 'p> Iterator for ExpandableStringSplit<'a, 'p> {
     type Item = ExpandableStrSplitResult<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(pending) = self.pending.take() {
+            return Some(pending);
+        }
+
         if self.done {
             return None;
         }
 
         while let Some((n, c)) = self.chars_iter.next() {
-            if c == '%' {
-                let reading_var = self.reading_var;
-                self.reading_var = !reading_var;
-                if n > 0 {
-                    let token_slice = &self.src[self.token_start..n];
-                    self.token_start = n + 1;
-                    if !token_slice.is_empty() {
-                        if reading_var {
-                            return Some(Ok(ExpandableStrEntry::Var(token_slice)));
-                        } else {
-                            return Some(Ok(ExpandableStrEntry::Substr(token_slice)));
-                        }
+            if !self.reading_var && c == self.open {
+                if matches!(self.chars_iter.peek(), Some((_, c2)) if *c2 == self.open) {
+                    let (escaped_at, _) = self.chars_iter.next().expect("peeked Some above");
+                    let before = &self.src[self.token_start..n];
+                    let literal = &self.src[n..n + self.open.len_utf8()];
+                    self.token_start = escaped_at + self.open.len_utf8();
+                    if before.is_empty() {
+                        return Some(Ok(ExpandableStrEntry::Substr(literal)));
                     }
-                } else {
-                    self.token_start = 1;
+                    self.pending = Some(Ok(ExpandableStrEntry::Substr(literal)));
+                    return Some(Ok(ExpandableStrEntry::Substr(before)));
                 }
+
+                let token_slice = &self.src[self.token_start..n];
+                self.token_start = n + self.open.len_utf8();
+                self.reading_var = true;
+                if !token_slice.is_empty() {
+                    return Some(Ok(ExpandableStrEntry::Substr(token_slice)));
+                }
+            } else if self.reading_var && c == self.close {
+                let token_slice = &self.src[self.token_start..n];
+                self.token_start = n + self.close.len_utf8();
+                self.reading_var = false;
+                return Some(self.parse_var_token(token_slice));
             }
         }
 
@@ -76,13 +201,47 @@ impl<'a> Iterator for ExpandableStringSplit<'a> {
     }
 }
 
+/// A named source of variable values, e.g. an explicit map, the process
+/// environment, or a [`ChainedSource`] layering several of those together.
 pub trait NamedValuesSource {
-    fn get(&self, key: &str) -> Option<&str>;
+    fn get(&self, key: &str) -> Option<Cow<'_, str>>;
+}
+
+impl NamedValuesSource for HashMap<String, String> {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        HashMap::get(self, key).map(|value| Cow::Borrowed(value.as_str()))
+    }
+}
+
+impl NamedValuesSource for HashMap<&str, &str> {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        HashMap::get(self, key).map(|value| Cow::Borrowed(*value))
+    }
+}
+
+/// Layers several [`NamedValuesSource`]s, resolving a name to the value of
+/// the first source (in order) that has one — e.g. explicit overrides, then
+/// process environment, then a set of defaults.
+pub struct ChainedSource<'a> {
+    sources: Vec<&'a dyn NamedValuesSource>,
+}
+
+impl<'a> ChainedSource<'a> {
+    pub fn new(sources: Vec<&'a dyn NamedValuesSource>) -> Self {
+        Self { sources }
+    }
+}
+
+impl<'a> NamedValuesSource for ChainedSource<'a> {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.sources.iter().find_map(|source| source.get(key))
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum ExpandStringError<'a> {
     InvalidFormat,
+    InvalidVariableName,
     MissingVariable(&'a str),
 }
 
@@ -90,6 +249,7 @@ impl<'a> std::convert::From<ExpandableStrSplitError> for ExpandStringError<'a> {
     fn from(src: ExpandableStrSplitError) -> Self {
         match src {
             ExpandableStrSplitError::InvalidFormat => Self::InvalidFormat,
+            ExpandableStrSplitError::InvalidVariableName => Self::InvalidVariableName,
         }
     }
 }
@@ -106,29 +266,138 @@ where
             ExpandableStrEntry::Substr(s) => {
                 expanded_str += s;
             }
-            ExpandableStrEntry::Var(id) => {
-                let val = get_value(id).ok_or(ExpandStringError::MissingVariable(id))?;
-                expanded_str += val.as_ref();
+            ExpandableStrEntry::Var { name, default } => match get_value(name) {
+                Some(val) => expanded_str += val.as_ref(),
+                None => match default {
+                    Some(default) => expanded_str += default,
+                    None => return Err(ExpandStringError::MissingVariable(name)),
+                },
+            },
+        }
+    }
+
+    Ok(expanded_str)
+}
+
+/// Like [`expand_string_with_values`], but resolving variables through a
+/// [`NamedValuesSource`] instead of a closure.
+pub fn expand_string_with_source<'a, S: NamedValuesSource>(
+    s: &'a str,
+    source: &S,
+) -> Result<String, ExpandStringError<'a>> {
+    expand_string_with_values(s, |key| source.get(key))
+}
+
+/// A depth limit to pass as `max_depth` to
+/// [`expand_string_recursively_with_values`] when the caller doesn't have a
+/// more specific limit in mind; the function itself always takes an explicit
+/// `max_depth`.
+pub const DEFAULT_MAX_RECURSION_DEPTH: usize = 64;
+
+/// Mirrors [`ExpandStringError`], but owns its strings: a recursive expansion
+/// walks into values produced by `get_value`, which may not live as long as
+/// the source string or the lookup closure itself.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ExpandStringRecursiveError {
+    InvalidFormat,
+    InvalidVariableName,
+    MissingVariable(String),
+    /// A variable's value (transitively) expands back to a reference to
+    /// itself; names the variable that was seen again in its own chain.
+    CyclicReference(String),
+    /// Expansion nested past the configured maximum depth.
+    MaxDepthExceeded,
+}
+
+impl std::convert::From<ExpandableStrSplitError> for ExpandStringRecursiveError {
+    fn from(src: ExpandableStrSplitError) -> Self {
+        match src {
+            ExpandableStrSplitError::InvalidFormat => Self::InvalidFormat,
+            ExpandableStrSplitError::InvalidVariableName => Self::InvalidVariableName,
+        }
+    }
+}
+
+/// Like [`expand_string_with_values`], but when a variable's value itself
+/// contains `%...%` references, those are expanded too, up to `max_depth`
+/// levels of nesting. A variable that reappears in its own expansion chain
+/// is reported as [`ExpandStringRecursiveError::CyclicReference`] rather
+/// than recursing forever.
+pub fn expand_string_recursively_with_values<F, S>(
+    s: &str,
+    get_value: F,
+    max_depth: usize,
+) -> Result<String, ExpandStringRecursiveError>
+where
+    F: Fn(&str) -> Option<S>,
+    S: AsRef<str>,
+{
+    let mut chain = Vec::new();
+    expand_recursively(s, &get_value, max_depth, &mut chain)
+}
+
+fn expand_recursively<F, S>(
+    s: &str,
+    get_value: &F,
+    depth_remaining: usize,
+    chain: &mut Vec<String>,
+) -> Result<String, ExpandStringRecursiveError>
+where
+    F: Fn(&str) -> Option<S>,
+    S: AsRef<str>,
+{
+    let mut expanded_str = String::with_capacity(s.len());
+
+    for entry in split_expandable_string(s) {
+        match entry? {
+            ExpandableStrEntry::Substr(s) => {
+                expanded_str += s;
             }
+            ExpandableStrEntry::Var { name, default } => match get_value(name) {
+                Some(val) => {
+                    if chain.iter().any(|seen| seen == name) {
+                        return Err(ExpandStringRecursiveError::CyclicReference(name.to_owned()));
+                    }
+                    let depth_remaining = depth_remaining
+                        .checked_sub(1)
+                        .ok_or(ExpandStringRecursiveError::MaxDepthExceeded)?;
+
+                    chain.push(name.to_owned());
+                    let result =
+                        expand_recursively(val.as_ref(), get_value, depth_remaining, chain);
+                    chain.pop();
+                    expanded_str += &result?;
+                }
+                None => match default {
+                    Some(default) => expanded_str += default,
+                    None => {
+                        return Err(ExpandStringRecursiveError::MissingVariable(name.to_owned()))
+                    }
+                },
+            },
         }
     }
 
     Ok(expanded_str)
 }
 
+/// A [`NamedValuesSource`] backed by the process environment.
 #[cfg(feature = "env")]
-pub fn expand_string_with_env(s: &str) -> Result<String, ExpandStringError> {
-    fn get_var_value(key: &str) -> Option<String> {
-        use std::ffi::{OsString, OsStr};
+pub struct EnvSource;
 
+#[cfg(feature = "env")]
+impl NamedValuesSource for EnvSource {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
         std::env::var_os(key)
-            .as_ref()
-            .map(OsString::as_os_str)
-            .map(OsStr::to_string_lossy)
-            .map(Into::into)
+            .as_deref()
+            .map(std::ffi::OsStr::to_string_lossy)
+            .map(|value| Cow::Owned(value.into_owned()))
     }
+}
 
-    expand_string_with_values(s, get_var_value)
+#[cfg(feature = "env")]
+pub fn expand_string_with_env(s: &str) -> Result<String, ExpandStringError> {
+    expand_string_with_source(s, &EnvSource)
 }
 
 #[cfg(test)]
@@ -142,7 +411,16 @@ mod tests {
         let x: Vec<_> = split_expandable_string(src)
             .filter_map(Result::ok)
             .collect();
-        assert_eq!(x, vec![Substr("foo"), Var("bar")]);
+        assert_eq!(
+            x,
+            vec![
+                Substr("foo"),
+                Var {
+                    name: "bar",
+                    default: None
+                }
+            ]
+        );
     }
 
     #[test]
@@ -151,7 +429,16 @@ mod tests {
         let x: Vec<_> = split_expandable_string(src)
             .filter_map(Result::ok)
             .collect();
-        assert_eq!(x, vec![Var("foo"), Substr("bar")]);
+        assert_eq!(
+            x,
+            vec![
+                Var {
+                    name: "foo",
+                    default: None
+                },
+                Substr("bar")
+            ]
+        );
     }
 
     #[test]
@@ -160,7 +447,19 @@ mod tests {
         let x: Vec<_> = split_expandable_string(src)
             .filter_map(Result::ok)
             .collect();
-        assert_eq!(x, vec![Var("foo"), Var("bar")]);
+        assert_eq!(
+            x,
+            vec![
+                Var {
+                    name: "foo",
+                    default: None
+                },
+                Var {
+                    name: "bar",
+                    default: None
+                }
+            ]
+        );
     }
 
     #[test]
@@ -183,4 +482,291 @@ mod tests {
         let x = expand_string_with_values(src, |id| values.get(id)).unwrap();
         assert_eq!(x, "This is a string with a a cup of tea and some cookies.");
     }
+
+    #[test]
+    fn splits_var_with_default() {
+        let src = "%USER:-anonymous%";
+        let x: Vec<_> = split_expandable_string(src)
+            .filter_map(Result::ok)
+            .collect();
+        assert_eq!(
+            x,
+            vec![Var {
+                name: "USER",
+                default: Some("anonymous")
+            }]
+        );
+    }
+
+    #[test]
+    fn splits_var_with_empty_default() {
+        let src = "%USER:-%";
+        let x: Vec<_> = split_expandable_string(src)
+            .filter_map(Result::ok)
+            .collect();
+        assert_eq!(
+            x,
+            vec![Var {
+                name: "USER",
+                default: Some("")
+            }]
+        );
+    }
+
+    #[test]
+    fn fails_to_parse_empty_name_before_default() {
+        let src = "%:-anonymous%";
+        let x: Vec<_> = split_expandable_string(src).collect();
+        assert_eq!(x, vec![Err(ExpandableStrSplitError::InvalidVariableName)]);
+    }
+
+    #[test]
+    fn uses_default_when_variable_missing() {
+        let src = "Hi %USER:-anonymous%!";
+        let x = expand_string_with_values(src, |_| -> Option<&str> { None }).unwrap();
+        assert_eq!(x, "Hi anonymous!");
+    }
+
+    #[test]
+    fn prefers_value_over_default_when_present() {
+        let values = {
+            let mut values = HashMap::new();
+            values.insert("USER", "alice");
+            values
+        };
+
+        let src = "Hi %USER:-anonymous%!";
+        let x = expand_string_with_values(src, |id| values.get(id)).unwrap();
+        assert_eq!(x, "Hi alice!");
+    }
+
+    #[test]
+    fn reports_missing_variable_without_default() {
+        let src = "Some %FOO%";
+        let x = expand_string_with_values(src, |_| -> Option<&str> { None });
+        assert_eq!(x, Err(ExpandStringError::MissingVariable("FOO")));
+    }
+
+    #[test]
+    fn escapes_doubled_percent() {
+        let src = "100%% sure";
+        let x: Vec<_> = split_expandable_string(src)
+            .filter_map(Result::ok)
+            .collect();
+        assert_eq!(x, vec![Substr("100"), Substr("%"), Substr(" sure")]);
+
+        let x = expand_string_with_values(src, |_| -> Option<&str> { None }).unwrap();
+        assert_eq!(x, "100% sure");
+    }
+
+    #[test]
+    fn escapes_percent_around_var() {
+        let src = "%%VAR%%";
+        let x: Vec<_> = split_expandable_string(src)
+            .filter_map(Result::ok)
+            .collect();
+        assert_eq!(x, vec![Substr("%"), Substr("VAR"), Substr("%")]);
+
+        let x = expand_string_with_values(src, |_| -> Option<&str> { None }).unwrap();
+        assert_eq!(x, "%VAR%");
+    }
+
+    #[test]
+    fn escapes_mixed_with_real_var() {
+        let src = "%%%foo%%%";
+        let x: Vec<_> = split_expandable_string(src)
+            .filter_map(Result::ok)
+            .collect();
+        assert_eq!(
+            x,
+            vec![
+                Substr("%"),
+                Var {
+                    name: "foo",
+                    default: None
+                },
+                Substr("%")
+            ]
+        );
+    }
+
+    #[test]
+    fn expands_recursively() {
+        let values = {
+            let mut values = HashMap::new();
+            values.insert("GREETING", "Hi %NAME%");
+            values.insert("NAME", "World");
+            values
+        };
+
+        let src = "%GREETING%!";
+        let x = expand_string_recursively_with_values(
+            src,
+            |id| values.get(id).copied(),
+            DEFAULT_MAX_RECURSION_DEPTH,
+        )
+        .unwrap();
+        assert_eq!(x, "Hi World!");
+    }
+
+    #[test]
+    fn recursive_expansion_uses_default_for_missing_variable() {
+        let values = {
+            let mut values = HashMap::new();
+            values.insert("GREETING", "Hi %NAME:-stranger%");
+            values
+        };
+
+        let x = expand_string_recursively_with_values(
+            "%GREETING%!",
+            |id| values.get(id).copied(),
+            DEFAULT_MAX_RECURSION_DEPTH,
+        )
+        .unwrap();
+        assert_eq!(x, "Hi stranger!");
+    }
+
+    #[test]
+    fn detects_cyclic_reference() {
+        let values = {
+            let mut values = HashMap::new();
+            values.insert("A", "%B%");
+            values.insert("B", "%A%");
+            values
+        };
+
+        let x = expand_string_recursively_with_values(
+            "%A%",
+            |id| values.get(id).copied(),
+            DEFAULT_MAX_RECURSION_DEPTH,
+        );
+        assert_eq!(
+            x,
+            Err(ExpandStringRecursiveError::CyclicReference("A".to_owned()))
+        );
+    }
+
+    #[test]
+    fn reports_max_depth_exceeded() {
+        let values = {
+            let mut values = HashMap::new();
+            values.insert("A", "%A%");
+            values
+        };
+
+        let x = expand_string_recursively_with_values("%A%", |id| values.get(id).copied(), 0);
+        assert_eq!(x, Err(ExpandStringRecursiveError::MaxDepthExceeded));
+    }
+
+    #[test]
+    fn expands_string_with_source() {
+        let mut values = HashMap::new();
+        values.insert("DRINK".to_owned(), "a cup of tea".to_owned());
+
+        let x = expand_string_with_source("Have %DRINK%.", &values).unwrap();
+        assert_eq!(x, "Have a cup of tea.");
+    }
+
+    #[test]
+    fn chained_source_prefers_earlier_sources() {
+        let overrides: HashMap<&str, &str> = [("DRINK", "coffee")].into_iter().collect();
+        let mut defaults = HashMap::new();
+        defaults.insert("DRINK".to_owned(), "a cup of tea".to_owned());
+        defaults.insert("FOOD".to_owned(), "cookies".to_owned());
+
+        let source = ChainedSource::new(vec![&overrides, &defaults]);
+
+        let x = expand_string_with_source("Have %DRINK% and %FOOD%.", &source).unwrap();
+        assert_eq!(x, "Have coffee and cookies.");
+    }
+
+    #[test]
+    fn chained_source_falls_through_when_missing() {
+        let overrides: HashMap<&str, &str> = HashMap::new();
+        let source = ChainedSource::new(vec![&overrides]);
+
+        let x = expand_string_with_source("%FOO%", &source);
+        assert_eq!(x, Err(ExpandStringError::MissingVariable("FOO")));
+    }
+
+    #[test]
+    fn fails_to_parse_invalid_var_name() {
+        let src = "Some %FOO BAR% here";
+        let x: Vec<_> = split_expandable_string(src).collect();
+        assert_eq!(x[1], Err(ExpandableStrSplitError::InvalidVariableName));
+
+        let src = "Some %FOO=BAR% here";
+        let x: Vec<_> = split_expandable_string(src).collect();
+        assert_eq!(x[1], Err(ExpandableStrSplitError::InvalidVariableName));
+    }
+
+    #[test]
+    fn splits_string_with_custom_delimiter() {
+        let src = "foo@bar@";
+        let options = ExpandOptions::new().delimiter('@');
+        let x: Vec<_> = split_expandable_string_with_options(src, options)
+            .filter_map(Result::ok)
+            .collect();
+        assert_eq!(
+            x,
+            vec![
+                Substr("foo"),
+                Var {
+                    name: "bar",
+                    default: None
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_string_with_brace_delimiters() {
+        let src = "foo{bar}";
+        let options = ExpandOptions::new().open_close_delimiters('{', '}');
+        let x: Vec<_> = split_expandable_string_with_options(src, options)
+            .filter_map(Result::ok)
+            .collect();
+        assert_eq!(
+            x,
+            vec![
+                Substr("foo"),
+                Var {
+                    name: "bar",
+                    default: None
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn fails_to_parse_empty_var_with_distinct_delimiters() {
+        let src = "foo{}bar";
+        let options = ExpandOptions::new().open_close_delimiters('{', '}');
+        let x: Vec<_> = split_expandable_string_with_options(src, options).collect();
+        assert_eq!(
+            x,
+            vec![
+                Ok(Substr("foo")),
+                Err(ExpandableStrSplitError::InvalidVariableName),
+                Ok(Substr("bar"))
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_string_with_custom_name_policy() {
+        let src = "%foo-bar%";
+        let options =
+            ExpandOptions::new().name_char_predicate(|c: char| c.is_ascii_alphabetic() || c == '-');
+        let x: Vec<_> = split_expandable_string_with_options(src, options)
+            .filter_map(Result::ok)
+            .collect();
+        assert_eq!(
+            x,
+            vec![Var {
+                name: "foo-bar",
+                default: None
+            }]
+        );
+    }
 }